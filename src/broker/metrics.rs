@@ -0,0 +1,102 @@
+use prometheus::{
+    Encoder, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+
+    pub static ref ACTIVE_DELETE_KEYS_TASKS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "undermoon_active_delete_keys_tasks",
+            "Whether a delete-keys task for a given db/node is currently active"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+
+    pub static ref DELETE_KEYS_SCAN_CURSOR: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "undermoon_delete_keys_scan_cursor",
+            "Current SCAN cursor position of a delete-keys task"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+
+    pub static ref DELETE_KEYS_SLOT_RANGES: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "undermoon_delete_keys_slot_ranges",
+            "Number of slot ranges a delete-keys task is purging"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+
+    pub static ref KEYS_SCANNED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "undermoon_keys_scanned_total",
+            "Total number of keys scanned while purging migrated slots"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+
+    pub static ref KEYS_DELETED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "undermoon_keys_deleted_total",
+            "Total number of keys deleted while purging migrated slots"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+
+    pub static ref METASTORE_EPOCH: IntGauge = IntGauge::new(
+        "undermoon_metastore_epoch",
+        "Current local metastore epoch"
+    )
+    .unwrap();
+
+    /// Current lifecycle state of a migration task for a given db/node, so
+    /// operators can alert on a task that's stuck instead of progressing
+    /// through `MIGRATION_STATE_*`. One gauge per (db, address) rather than
+    /// per state, since a task is only ever in exactly one state at a time.
+    /// Only the delete-keys task map sets it, to `MIGRATION_STATE_DELETING_KEYS`
+    /// or `MIGRATION_STATE_FINISHED`; no code sets the migrating or
+    /// importing values.
+    pub static ref MIGRATION_TASK_STATE: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "undermoon_migration_task_state",
+            "Lifecycle state of a migration task: 0=migrating, 1=importing, 2=deleting_keys, 3=finished"
+        ),
+        &["db", "address"],
+    )
+    .unwrap();
+}
+
+pub const MIGRATION_STATE_MIGRATING: i64 = 0;
+pub const MIGRATION_STATE_IMPORTING: i64 = 1;
+pub const MIGRATION_STATE_DELETING_KEYS: i64 = 2;
+pub const MIGRATION_STATE_FINISHED: i64 = 3;
+
+/// Registers every metric declared above with the global registry. Safe to
+/// call more than once; later calls are no-ops.
+pub fn register_metrics() {
+    let _ = REGISTRY.register(Box::new(ACTIVE_DELETE_KEYS_TASKS.clone()));
+    let _ = REGISTRY.register(Box::new(DELETE_KEYS_SCAN_CURSOR.clone()));
+    let _ = REGISTRY.register(Box::new(DELETE_KEYS_SLOT_RANGES.clone()));
+    let _ = REGISTRY.register(Box::new(KEYS_SCANNED_TOTAL.clone()));
+    let _ = REGISTRY.register(Box::new(KEYS_DELETED_TOTAL.clone()));
+    let _ = REGISTRY.register(Box::new(METASTORE_EPOCH.clone()));
+    let _ = REGISTRY.register(Box::new(MIGRATION_TASK_STATE.clone()));
+}
+
+/// Renders every registered metric in Prometheus text exposition format.
+pub fn gather_metrics() -> String {
+    let encoder = TextEncoder::new();
+    let metric_families = REGISTRY.gather();
+    let mut buffer = Vec::new();
+    if encoder.encode(&metric_families, &mut buffer).is_err() {
+        return String::new();
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
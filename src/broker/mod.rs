@@ -1,6 +1,7 @@
 mod epoch;
 mod external;
 mod migrate;
+pub mod metrics;
 mod persistence;
 mod query;
 mod replication;
@@ -14,7 +15,7 @@ mod ordered_proxy;
 mod utils;
 
 pub use self::persistence::{JsonFileStorage, MetaPersistence, MetaSyncError};
-pub use self::replication::{JsonMetaReplicator, MetaReplicator};
+pub use self::replication::{JsonMetaReplicator, MerkleMetaReplicator, MetaReplicator};
 pub use self::service::{
     run_server, MemBrokerConfig, MemBrokerService, ReplicaAddresses, StorageConfig,
     MEM_BROKER_API_VERSION,
@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashMap};
+use std::pin::Pin;
+
+use crc64::crc64;
+use futures::{Future, FutureExt};
+use reqwest::Client;
+use serde_json;
+
+use crate::broker::store::{ClusterRecord, MetaStore, MetaStoreError};
+
+/// Depth of the Merkle tree, i.e. how many nibbles of a cluster name's hash
+/// are used to route it to a bucket before leaves are compared individually.
+pub const MERKLE_DEPTH: usize = 4;
+
+pub type NodeHash = u64;
+
+#[derive(Debug)]
+pub enum ReplicationError {
+    StoreError(MetaStoreError),
+    Rpc(String),
+    VersionMismatch,
+}
+
+impl From<MetaStoreError> for ReplicationError {
+    fn from(err: MetaStoreError) -> Self {
+        ReplicationError::StoreError(err)
+    }
+}
+
+pub type ReplicationFuture<T> = Pin<Box<dyn Future<Output = Result<T, ReplicationError>> + Send>>;
+
+fn hash_bytes(data: &[u8]) -> NodeHash {
+    crc64(0, data)
+}
+
+fn hash_cluster_record(record: &ClusterRecord) -> NodeHash {
+    // `ClusterRecord::nodes` is a `HashMap`, whose serde_json serialization
+    // order depends on iteration order rather than content, so hashing the
+    // record directly would make the leaf hash unstable across brokers and
+    // even across re-serializations of the same broker. Route the map
+    // through a `BTreeMap` first so the bytes we hash only depend on what's
+    // in the record, not how it happens to be laid out in memory.
+    let canonical_nodes: BTreeMap<&String, &String> = record.nodes.value.iter().collect();
+    let canonical = (
+        &record.name,
+        &record.slot_ranges.timestamp,
+        &record.slot_ranges.value,
+        &record.nodes.timestamp,
+        &canonical_nodes,
+    );
+    let bytes = serde_json::to_vec(&canonical).unwrap_or_default();
+    hash_bytes(&bytes)
+}
+
+/// Which bucket (by nibble prefix of `crc64(cluster_name)`) a cluster name
+/// belongs to at each level of the tree.
+fn bucket_path(cluster_name: &str) -> Vec<u8> {
+    let full = hash_bytes(cluster_name.as_bytes());
+    (0..MERKLE_DEPTH)
+        .map(|i| ((full >> (i * 4)) & 0xf) as u8)
+        .collect()
+}
+
+fn combine_hashes(hashes: &[NodeHash]) -> NodeHash {
+    let mut sorted = hashes.to_vec();
+    sorted.sort_unstable();
+    let mut data = Vec::with_capacity(sorted.len() * 8);
+    for h in sorted {
+        data.extend_from_slice(&h.to_le_bytes());
+    }
+    hash_bytes(&data)
+}
+
+/// A fixed-depth Merkle tree over a `MetaStore`'s cluster records, keyed by a
+/// prefix of `crc64(cluster_name)`. Two metastores holding the same clusters
+/// always produce the same tree, independent of insertion order, so brokers
+/// can compare root hashes and only descend into subtrees that differ.
+pub struct MerkleTree {
+    // `nodes[level]` maps a path (of `level` nibbles) to the hash of the
+    // subtree rooted at that path. `nodes[MERKLE_DEPTH]` holds leaf hashes,
+    // one per cluster name bucket.
+    nodes: Vec<HashMap<Vec<u8>, NodeHash>>,
+    leaves: HashMap<Vec<u8>, Vec<String>>,
+}
+
+impl MerkleTree {
+    pub fn build(store: &MetaStore) -> Self {
+        let mut leaves: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+        let mut leaf_hashes: HashMap<Vec<u8>, NodeHash> = HashMap::new();
+        let mut record_hashes: HashMap<Vec<u8>, Vec<NodeHash>> = HashMap::new();
+
+        let mut names = store.cluster_names();
+        names.sort();
+        for name in names {
+            let record = match store.get_cluster_record(&name) {
+                Some(record) => record,
+                None => continue,
+            };
+            let path = bucket_path(&name);
+            record_hashes
+                .entry(path.clone())
+                .or_insert_with(Vec::new)
+                .push(hash_cluster_record(&record));
+            leaves.entry(path).or_insert_with(Vec::new).push(name);
+        }
+        for (path, hashes) in record_hashes.into_iter() {
+            leaf_hashes.insert(path, combine_hashes(&hashes));
+        }
+
+        let mut nodes = vec![HashMap::new(); MERKLE_DEPTH + 1];
+        nodes[MERKLE_DEPTH] = leaf_hashes;
+        for level in (0..MERKLE_DEPTH).rev() {
+            let mut parents: HashMap<Vec<u8>, Vec<NodeHash>> = HashMap::new();
+            for (path, hash) in nodes[level + 1].iter() {
+                let parent_path = path[..level].to_vec();
+                parents
+                    .entry(parent_path)
+                    .or_insert_with(Vec::new)
+                    .push(*hash);
+            }
+            nodes[level] = parents
+                .into_iter()
+                .map(|(path, hashes)| (path, combine_hashes(&hashes)))
+                .collect();
+        }
+
+        Self { nodes, leaves }
+    }
+
+    pub fn root_hash(&self) -> NodeHash {
+        *self.nodes[0].get(&Vec::new()).unwrap_or(&0)
+    }
+
+    pub fn child_hashes(&self, path: &[u8]) -> Vec<(u8, NodeHash)> {
+        let level = path.len() + 1;
+        if level > MERKLE_DEPTH {
+            return Vec::new();
+        }
+        (0u8..16)
+            .filter_map(|nibble| {
+                let mut child_path = path.to_vec();
+                child_path.push(nibble);
+                self.nodes[level].get(&child_path).map(|h| (nibble, *h))
+            })
+            .collect()
+    }
+
+    pub fn clusters_at_leaf(&self, path: &[u8]) -> Vec<String> {
+        self.leaves.get(path).cloned().unwrap_or_default()
+    }
+}
+
+/// Transport used to exchange Merkle nodes and cluster records with a peer
+/// broker. Kept separate from `MetaReplicator` so the sync algorithm stays
+/// agnostic of how bytes actually move over the wire.
+pub trait ReplicationTransport: Send + Sync {
+    fn fetch_root(&self, peer_address: &str) -> ReplicationFuture<NodeHash>;
+    fn fetch_children(
+        &self,
+        peer_address: &str,
+        path: Vec<u8>,
+    ) -> ReplicationFuture<Vec<(u8, NodeHash)>>;
+    /// The cluster names the peer has bucketed under this leaf path. Needed
+    /// because a cluster the peer created but we've never seen locally has
+    /// no entry on our side of the tree at all, so we can only learn its
+    /// name by asking the peer what it thinks belongs there.
+    fn fetch_leaf_clusters(&self, peer_address: &str, path: Vec<u8>) -> ReplicationFuture<Vec<String>>;
+    fn fetch_clusters(
+        &self,
+        peer_address: &str,
+        names: Vec<String>,
+    ) -> ReplicationFuture<Vec<ClusterRecord>>;
+    fn fetch_snapshot(&self, peer_address: &str) -> ReplicationFuture<MetaStore>;
+    fn fetch_schema_version(&self, peer_address: &str) -> ReplicationFuture<u32>;
+}
+
+#[derive(Clone)]
+pub struct HttpReplicationTransport {
+    client: Client,
+}
+
+impl HttpReplicationTransport {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+        }
+    }
+}
+
+impl ReplicationTransport for HttpReplicationTransport {
+    fn fetch_root(&self, peer_address: &str) -> ReplicationFuture<NodeHash> {
+        let client = self.client.clone();
+        let url = format!("http://{}/api/v2/replication/merkle/root", peer_address);
+        async move {
+            let hash = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(hash)
+        }
+        .boxed()
+    }
+
+    fn fetch_children(
+        &self,
+        peer_address: &str,
+        path: Vec<u8>,
+    ) -> ReplicationFuture<Vec<(u8, NodeHash)>> {
+        let client = self.client.clone();
+        let path_str: String = path.iter().map(|n| format!("{:x}", n)).collect();
+        let url = format!(
+            "http://{}/api/v2/replication/merkle/children/{}",
+            peer_address, path_str
+        );
+        async move {
+            let children = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(children)
+        }
+        .boxed()
+    }
+
+    fn fetch_leaf_clusters(&self, peer_address: &str, path: Vec<u8>) -> ReplicationFuture<Vec<String>> {
+        let client = self.client.clone();
+        let path_str: String = path.iter().map(|n| format!("{:x}", n)).collect();
+        let url = format!(
+            "http://{}/api/v2/replication/merkle/leaf/{}",
+            peer_address, path_str
+        );
+        async move {
+            let names = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(names)
+        }
+        .boxed()
+    }
+
+    fn fetch_clusters(
+        &self,
+        peer_address: &str,
+        names: Vec<String>,
+    ) -> ReplicationFuture<Vec<ClusterRecord>> {
+        let client = self.client.clone();
+        let url = format!("http://{}/api/v2/replication/clusters", peer_address);
+        async move {
+            let records = client
+                .post(&url)
+                .json(&names)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(records)
+        }
+        .boxed()
+    }
+
+    fn fetch_snapshot(&self, peer_address: &str) -> ReplicationFuture<MetaStore> {
+        let client = self.client.clone();
+        let url = format!("http://{}/api/v2/replication/snapshot", peer_address);
+        async move {
+            let store = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(store)
+        }
+        .boxed()
+    }
+
+    fn fetch_schema_version(&self, peer_address: &str) -> ReplicationFuture<u32> {
+        let client = self.client.clone();
+        let url = format!("http://{}/api/v2/replication/schema_version", peer_address);
+        async move {
+            let version = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?
+                .json()
+                .await
+                .map_err(|e| ReplicationError::Rpc(e.to_string()))?;
+            Ok(version)
+        }
+        .boxed()
+    }
+}
+
+/// Syncs local metadata against a peer broker, returning the cluster records
+/// that need to be pulled in from the peer to converge.
+pub trait MetaReplicator: Send + Sync {
+    fn fetch_diff(&self, peer_address: String, local: &MetaStore) -> ReplicationFuture<Vec<ClusterRecord>>;
+}
+
+/// Ships the whole metadata snapshot on every sync. Simple and always
+/// correct, but its cost is proportional to the total amount of metadata
+/// rather than to how much of it actually changed. Used for the initial
+/// bootstrap of an empty broker and as the fallback when a peer is running
+/// an incompatible metadata schema version.
+#[derive(Clone)]
+pub struct JsonMetaReplicator {
+    transport: HttpReplicationTransport,
+}
+
+impl JsonMetaReplicator {
+    pub fn new() -> Self {
+        Self {
+            transport: HttpReplicationTransport::new(),
+        }
+    }
+}
+
+impl MetaReplicator for JsonMetaReplicator {
+    fn fetch_diff(&self, peer_address: String, _local: &MetaStore) -> ReplicationFuture<Vec<ClusterRecord>> {
+        let fetch = self.transport.fetch_snapshot(&peer_address);
+        async move {
+            let remote = fetch.await?;
+            Ok(remote
+                .cluster_names()
+                .into_iter()
+                .filter_map(|name| remote.get_cluster_record(&name))
+                .collect())
+        }
+        .boxed()
+    }
+}
+
+/// Merkle-tree based anti-entropy sync. Two brokers first exchange only
+/// their root hashes; if they match, nothing needs to move. Otherwise the
+/// brokers recursively exchange child hashes and only descend into the
+/// subtrees that differ, eventually transferring just the cluster records
+/// behind the differing leaves. This keeps replication cost proportional to
+/// the size of the actual diff instead of the size of the whole metastore.
+#[derive(Clone)]
+pub struct MerkleMetaReplicator {
+    transport: HttpReplicationTransport,
+    fallback: JsonMetaReplicator,
+}
+
+impl MerkleMetaReplicator {
+    pub fn new() -> Self {
+        Self {
+            transport: HttpReplicationTransport::new(),
+            fallback: JsonMetaReplicator::new(),
+        }
+    }
+
+    // Explicitly boxed: this function calls itself, and an `async fn` that
+    // recurses without boxing would need to contain a copy of its own
+    // future inside itself, which doesn't have a finite size.
+    fn collect_diverging_clusters<'a>(
+        transport: HttpReplicationTransport,
+        peer_address: String,
+        local_tree: &'a MerkleTree,
+        path: Vec<u8>,
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<String>, ReplicationError>> + Send + 'a>> {
+        Box::pin(async move {
+            if path.len() == MERKLE_DEPTH {
+                // A cluster the peer created but we've never seen locally has
+                // no entry in our own leaf, so the diff must include whatever
+                // the peer reports here too, not just our own names.
+                let mut names = local_tree.clusters_at_leaf(&path);
+                let remote_names = transport.fetch_leaf_clusters(&peer_address, path.clone()).await?;
+                for name in remote_names {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                return Ok(names);
+            }
+
+            let local_by_nibble: HashMap<u8, NodeHash> =
+                local_tree.child_hashes(&path).into_iter().collect();
+            let remote_by_nibble: HashMap<u8, NodeHash> = transport
+                .fetch_children(&peer_address, path.clone())
+                .await?
+                .into_iter()
+                .collect();
+
+            // Union both sides: a nibble the peer has but we don't (or vice
+            // versa) must still be descended into, or newly-created/deleted
+            // clusters on either side would never be noticed.
+            let mut nibbles: Vec<u8> = local_by_nibble.keys().cloned().collect();
+            for nibble in remote_by_nibble.keys() {
+                if !nibbles.contains(nibble) {
+                    nibbles.push(*nibble);
+                }
+            }
+
+            let mut names = Vec::new();
+            for nibble in nibbles {
+                let diverges = local_by_nibble.get(&nibble) != remote_by_nibble.get(&nibble);
+                if !diverges {
+                    continue;
+                }
+                let mut child_path = path.clone();
+                child_path.push(nibble);
+                let diverging = Self::collect_diverging_clusters(
+                    transport.clone(),
+                    peer_address.clone(),
+                    local_tree,
+                    child_path,
+                )
+                .await?;
+                names.extend(diverging);
+            }
+            Ok(names)
+        })
+    }
+}
+
+impl MetaReplicator for MerkleMetaReplicator {
+    fn fetch_diff(
+        &self,
+        peer_address: String,
+        local: &MetaStore,
+    ) -> ReplicationFuture<Vec<ClusterRecord>> {
+        // Cloned up front: whether we can trust the peer's schema version is
+        // only known after an await below, by which point a borrowed
+        // `local` would no longer be valid to hold onto.
+        let local_owned = local.clone();
+        let local_tree = MerkleTree::build(&local_owned);
+        let transport = self.transport.clone();
+        let fallback = self.fallback.clone();
+        async move {
+            // The Merkle tree format is only comparable between brokers
+            // running the same schema version; a peer on a different
+            // version gets the always-correct full-snapshot path instead.
+            let peer_version = transport.fetch_schema_version(&peer_address).await?;
+            if peer_version != MetaStore::CURRENT_SCHEMA_VERSION {
+                return fallback.fetch_diff(peer_address, &local_owned).await;
+            }
+            // An empty broker needs everything, so bootstrap it from the
+            // full snapshot rather than walking the whole tree.
+            if local_owned.cluster_names().is_empty() {
+                return fallback.fetch_diff(peer_address, &local_owned).await;
+            }
+
+            let remote_root = transport.fetch_root(&peer_address).await?;
+            if remote_root == local_tree.root_hash() {
+                return Ok(Vec::new());
+            }
+
+            let names = Self::collect_diverging_clusters(
+                transport.clone(),
+                peer_address.clone(),
+                &local_tree,
+                Vec::new(),
+            )
+            .await?;
+            if names.is_empty() {
+                return Ok(Vec::new());
+            }
+            transport.fetch_clusters(&peer_address, names).await
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use serde_derive::Deserialize;
+use tokio::sync::{Notify, RwLock};
+
+use crate::broker::metrics;
+use crate::broker::persistence::{build_persistence, MetaPersistence, MetaSyncError, StorageBackendKind};
+use crate::broker::replication::{MerkleMetaReplicator, MerkleTree};
+use crate::broker::store::{ClusterRecord, MetaStore};
+use crate::broker::update::sync_with_peer;
+
+/// Bumped for the long-poll watch endpoint. `PREVIOUS_MEM_BROKER_API_VERSION`
+/// is kept registered alongside it so clients still pinned to the old
+/// version string keep reaching the plain snapshot GET unchanged; only the
+/// new watch endpoint is exclusive to the current version.
+pub const MEM_BROKER_API_VERSION: &str = "v3";
+const PREVIOUS_MEM_BROKER_API_VERSION: &str = "v2";
+
+const MAX_WATCH_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_WATCH_TIMEOUT: Duration = Duration::from_secs(30);
+const REPLICA_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub backend: StorageBackendKind,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicaAddresses {
+    pub addresses: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MemBrokerConfig {
+    pub broker_id: String,
+    pub address: String,
+    pub storage: StorageConfig,
+    pub replicas: ReplicaAddresses,
+}
+
+/// Holds the live `MetaStore` plus an `Notify` that's fired every time the
+/// epoch advances, so any number of long-polling watchers parked on it wake
+/// up at once instead of each polling on its own schedule.
+pub struct MemBrokerService {
+    config: MemBrokerConfig,
+    store: RwLock<MetaStore>,
+    persistence: Box<dyn MetaPersistence>,
+    epoch_changed: Notify,
+}
+
+impl MemBrokerService {
+    pub fn new(config: MemBrokerConfig) -> Result<Arc<Self>, MetaSyncError> {
+        let persistence = build_persistence(config.storage.backend, config.storage.path.clone())?;
+        let mut store = MetaStore::new(config.broker_id.clone());
+        for (_, record) in persistence.load_all()? {
+            store.upsert_cluster_record(record);
+        }
+        Ok(Arc::new(Self {
+            config,
+            store: RwLock::new(store),
+            persistence,
+            epoch_changed: Notify::new(),
+        }))
+    }
+
+    pub async fn snapshot(&self) -> MetaStore {
+        self.store.read().await.clone()
+    }
+
+    pub async fn epoch(&self) -> u64 {
+        self.store.read().await.epoch()
+    }
+
+    pub async fn apply_cluster_update(&self, record: ClusterRecord) -> Result<(), MetaSyncError> {
+        self.persistence.write_cluster(&record)?;
+        let epoch = {
+            let mut store = self.store.write().await;
+            store.upsert_cluster_record(record);
+            store.epoch()
+        };
+        metrics::METASTORE_EPOCH.set(epoch as i64);
+        self.epoch_changed.notify_waiters();
+        Ok(())
+    }
+
+    pub async fn merge_from_peer(&self, peer: MetaStore) -> bool {
+        let (changed, epoch) = {
+            let mut store = self.store.write().await;
+            let changed = store.merge(&peer);
+            (changed, store.epoch())
+        };
+        if changed {
+            metrics::METASTORE_EPOCH.set(epoch as i64);
+            self.epoch_changed.notify_waiters();
+        }
+        changed
+    }
+
+    /// Runs one round of Merkle anti-entropy sync against every configured
+    /// replica, applying whatever each peer reports as different via
+    /// `MetaStore::merge`.
+    pub async fn sync_with_replicas(&self) {
+        let replicator = MerkleMetaReplicator::new();
+        for address in self.config.replicas.addresses.clone() {
+            let (changed, epoch) = {
+                let mut store = self.store.write().await;
+                match sync_with_peer(&mut store, &replicator, address.clone()).await {
+                    Ok(changed) => (changed, store.epoch()),
+                    Err(err) => {
+                        error!("failed to sync metadata with {}: {:?}", address, err);
+                        continue;
+                    }
+                }
+            };
+            if changed {
+                metrics::METASTORE_EPOCH.set(epoch as i64);
+                self.epoch_changed.notify_waiters();
+            }
+        }
+    }
+
+    /// Blocks until the metastore epoch advances past `known_epoch`, up to
+    /// `timeout`. Returns the new snapshot if the epoch moved, or `None` if
+    /// the timeout elapsed with no change (the caller should reply as a
+    /// 304-style "no change").
+    pub async fn watch(&self, known_epoch: u64, timeout: Duration) -> Option<MetaStore> {
+        // A `Notified` future only enrolls as a waiter once it's polled, and
+        // `notify_waiters` wakes already-registered waiters rather than
+        // leaving a permit behind — so constructing `notified` alone does
+        // not close the race. `enable()` forces the registration itself (as
+        // if the future had been polled once) before we sample the epoch,
+        // so a bump landing between that call and `select!` is still seen.
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let notified = self.epoch_changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let current = self.snapshot().await;
+            if current.epoch() > known_epoch {
+                return Some(current);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return None;
+            }
+
+            tokio::select! {
+                _ = notified => {}
+                _ = tokio::time::sleep_until(deadline) => {}
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WatchQuery {
+    epoch: u64,
+    timeout_secs: Option<u64>,
+}
+
+async fn get_metadata(service: web::Data<Arc<MemBrokerService>>) -> HttpResponse {
+    HttpResponse::Ok().json(service.snapshot().await)
+}
+
+async fn watch_metadata(
+    service: web::Data<Arc<MemBrokerService>>,
+    query: web::Query<WatchQuery>,
+) -> HttpResponse {
+    let timeout = query
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_WATCH_TIMEOUT)
+        .min(MAX_WATCH_TIMEOUT);
+
+    match service.watch(query.epoch, timeout).await {
+        Some(store) => HttpResponse::Ok().json(store),
+        None => HttpResponse::NotModified().finish(),
+    }
+}
+
+async fn get_prometheus_metrics() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::gather_metrics())
+}
+
+/// Path segments for the Merkle endpoints are the nibble path rendered as
+/// hex, e.g. `"2a"`, matching what `HttpReplicationTransport` sends. The
+/// root is the empty path, so the children route accepts an empty segment.
+fn parse_nibble_path(path: &str) -> Vec<u8> {
+    path.chars().filter_map(|c| c.to_digit(16)).map(|d| d as u8).collect()
+}
+
+async fn get_merkle_root(service: web::Data<Arc<MemBrokerService>>) -> HttpResponse {
+    let tree = MerkleTree::build(&service.snapshot().await);
+    HttpResponse::Ok().json(tree.root_hash())
+}
+
+async fn get_merkle_children(
+    service: web::Data<Arc<MemBrokerService>>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let nibbles = parse_nibble_path(&path);
+    let tree = MerkleTree::build(&service.snapshot().await);
+    HttpResponse::Ok().json(tree.child_hashes(&nibbles))
+}
+
+async fn get_merkle_leaf(
+    service: web::Data<Arc<MemBrokerService>>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let nibbles = parse_nibble_path(&path);
+    let tree = MerkleTree::build(&service.snapshot().await);
+    HttpResponse::Ok().json(tree.clusters_at_leaf(&nibbles))
+}
+
+async fn get_clusters_by_name(
+    service: web::Data<Arc<MemBrokerService>>,
+    names: web::Json<Vec<String>>,
+) -> HttpResponse {
+    let store = service.snapshot().await;
+    let records: Vec<ClusterRecord> = names
+        .into_inner()
+        .into_iter()
+        .filter_map(|name| store.get_cluster_record(&name))
+        .collect();
+    HttpResponse::Ok().json(records)
+}
+
+async fn get_schema_version(service: web::Data<Arc<MemBrokerService>>) -> HttpResponse {
+    HttpResponse::Ok().json(service.snapshot().await.schema_version())
+}
+
+pub fn run_server(service: Arc<MemBrokerService>) -> std::io::Result<actix_web::dev::Server> {
+    metrics::register_metrics();
+
+    let sync_service = service.clone();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(REPLICA_SYNC_INTERVAL);
+        loop {
+            ticker.tick().await;
+            sync_service.sync_with_replicas().await;
+        }
+    });
+
+    let address = service.config.address.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .data(service.clone())
+            .route(
+                &format!("/api/{}/metadata", MEM_BROKER_API_VERSION),
+                web::get().to(get_metadata),
+            )
+            .route(
+                &format!("/api/{}/metadata/watch", MEM_BROKER_API_VERSION),
+                web::get().to(watch_metadata),
+            )
+            .route(
+                &format!("/api/{}/metadata", PREVIOUS_MEM_BROKER_API_VERSION),
+                web::get().to(get_metadata),
+            )
+            .route("/metrics", web::get().to(get_prometheus_metrics))
+            .route(
+                "/api/v2/replication/merkle/root",
+                web::get().to(get_merkle_root),
+            )
+            .route(
+                "/api/v2/replication/merkle/children/{path:.*}",
+                web::get().to(get_merkle_children),
+            )
+            .route(
+                "/api/v2/replication/merkle/leaf/{path}",
+                web::get().to(get_merkle_leaf),
+            )
+            .route(
+                "/api/v2/replication/clusters",
+                web::post().to(get_clusters_by_name),
+            )
+            .route(
+                "/api/v2/replication/snapshot",
+                web::get().to(get_metadata),
+            )
+            .route(
+                "/api/v2/replication/schema_version",
+                web::get().to(get_schema_version),
+            )
+    })
+    .bind(address)?
+    .run();
+    Ok(server)
+}
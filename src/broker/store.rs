@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::common::cluster::SlotRange;
+
+#[derive(Debug)]
+pub enum MetaStoreError {
+    ClusterNotFound(String),
+    InvalidMeta(String),
+}
+
+/// A CRDT logical clock: `(wall_clock_millis, broker_id)`. Ties on the wall
+/// clock are broken by `broker_id` so the ordering is always total, which is
+/// what lets two brokers merge their state without coordination.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Timestamp {
+    pub wall_clock_millis: u64,
+    pub broker_id: String,
+}
+
+impl Timestamp {
+    pub fn now(broker_id: String) -> Self {
+        let wall_clock_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Self {
+            wall_clock_millis,
+            broker_id,
+        }
+    }
+}
+
+/// A last-writer-wins register: holds a value together with the logical
+/// timestamp of the write that produced it. Merging two registers keeps
+/// whichever has the larger timestamp.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LwwRegister<T> {
+    pub value: T,
+    pub timestamp: Timestamp,
+}
+
+impl<T: Clone> LwwRegister<T> {
+    pub fn new(value: T, timestamp: Timestamp) -> Self {
+        Self { value, timestamp }
+    }
+
+    pub fn set(&mut self, value: T, timestamp: Timestamp) {
+        if timestamp > self.timestamp {
+            self.value = value;
+            self.timestamp = timestamp;
+        }
+    }
+
+    /// Merges `other` into `self`, keeping the value with the larger
+    /// timestamp. Returns whether `self` changed.
+    pub fn merge(&mut self, other: &Self) -> bool {
+        if other.timestamp > self.timestamp {
+            self.value = other.value.clone();
+            self.timestamp = other.timestamp.clone();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The per-cluster record, with every mutable field carrying its own LWW
+/// timestamp so concurrent updates to different fields from different
+/// brokers never clobber each other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterRecord {
+    pub name: String,
+    pub slot_ranges: LwwRegister<Vec<SlotRange>>,
+    pub nodes: LwwRegister<HashMap<String, String>>,
+}
+
+impl ClusterRecord {
+    pub fn new(
+        name: String,
+        slot_ranges: Vec<SlotRange>,
+        nodes: HashMap<String, String>,
+        timestamp: Timestamp,
+    ) -> Self {
+        Self {
+            name,
+            slot_ranges: LwwRegister::new(slot_ranges, timestamp.clone()),
+            nodes: LwwRegister::new(nodes, timestamp),
+        }
+    }
+
+    /// Merges `other` into `self` field by field. Returns whether anything
+    /// changed.
+    pub fn merge(&mut self, other: &Self) -> bool {
+        let slots_changed = self.slot_ranges.merge(&other.slot_ranges);
+        let nodes_changed = self.nodes.merge(&other.nodes);
+        slots_changed || nodes_changed
+    }
+}
+
+/// A CRDT map from cluster name to `ClusterRecord`, modeled as an
+/// observed-remove map with LWW tombstones: an entry is considered deleted
+/// once a tombstone with a strictly greater timestamp than the entry's
+/// newest field timestamp has been recorded for it. Merging two metastores
+/// is therefore always commutative and convergent, so two brokers that each
+/// accepted writes during a network partition reach the same state once
+/// they reconnect, without either one's writes being silently dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaStore {
+    broker_id: String,
+    local_epoch: u64,
+    clusters: HashMap<String, ClusterRecord>,
+    tombstones: HashMap<String, Timestamp>,
+}
+
+impl MetaStore {
+    pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+    pub fn new(broker_id: String) -> Self {
+        Self {
+            broker_id,
+            local_epoch: 0,
+            clusters: HashMap::new(),
+            tombstones: HashMap::new(),
+        }
+    }
+
+    pub fn schema_version(&self) -> u32 {
+        Self::CURRENT_SCHEMA_VERSION
+    }
+
+    /// A local, monotonically increasing version number bumped on every
+    /// mutation. It has no cross-broker meaning by itself; it only exists so
+    /// that watchers of this broker can cheaply detect "something changed"
+    /// without comparing full snapshots.
+    pub fn epoch(&self) -> u64 {
+        self.local_epoch
+    }
+
+    pub fn cluster_names(&self) -> Vec<String> {
+        self.clusters.keys().cloned().collect()
+    }
+
+    pub fn get_cluster_record(&self, name: &str) -> Option<ClusterRecord> {
+        self.clusters.get(name).cloned()
+    }
+
+    /// Inserts or merges a single cluster record, used both for local writes
+    /// and for applying records pulled in by a replicator.
+    pub fn upsert_cluster_record(&mut self, record: ClusterRecord) {
+        let tombstoned = self
+            .tombstones
+            .get(&record.name)
+            .map(|deleted_at| *deleted_at > record.slot_ranges.timestamp && *deleted_at > record.nodes.timestamp)
+            .unwrap_or(false);
+        if tombstoned {
+            return;
+        }
+
+        let changed = match self.clusters.get_mut(&record.name) {
+            Some(existing) => existing.merge(&record),
+            None => {
+                self.clusters.insert(record.name.clone(), record);
+                true
+            }
+        };
+        if changed {
+            self.local_epoch += 1;
+        }
+    }
+
+    pub fn remove_cluster(&mut self, name: &str) -> Result<(), MetaStoreError> {
+        if !self.clusters.contains_key(name) {
+            return Err(MetaStoreError::ClusterNotFound(name.to_string()));
+        }
+        self.clusters.remove(name);
+        self.tombstones
+            .insert(name.to_string(), Timestamp::now(self.broker_id.clone()));
+        self.local_epoch += 1;
+        Ok(())
+    }
+
+    /// Merges `other` into `self`: every field of every cluster record takes
+    /// the value with the larger logical timestamp, and tombstones win over
+    /// stale records. This is what the replicator calls instead of blindly
+    /// overwriting local state with whatever a peer sends.
+    pub fn merge(&mut self, other: &MetaStore) -> bool {
+        let mut changed = false;
+
+        for (name, deleted_at) in other.tombstones.iter() {
+            let should_delete = self
+                .clusters
+                .get(name)
+                .map(|record| {
+                    *deleted_at > record.slot_ranges.timestamp && *deleted_at > record.nodes.timestamp
+                })
+                .unwrap_or(true);
+            let newer_tombstone = self
+                .tombstones
+                .get(name)
+                .map(|existing| *deleted_at > *existing)
+                .unwrap_or(true);
+            if newer_tombstone {
+                self.tombstones.insert(name.clone(), deleted_at.clone());
+                changed = true;
+            }
+            if should_delete && self.clusters.remove(name).is_some() {
+                changed = true;
+            }
+        }
+
+        for (name, record) in other.clusters.iter() {
+            // Don't skip on `self.tombstones.contains_key(name)`: a tombstone
+            // only wins over field timestamps strictly older than it, per
+            // `upsert_cluster_record`, so a record re-created after a
+            // delete (with a newer timestamp) must still be allowed through
+            // here or recreate-after-delete could never converge.
+            let epoch_before = self.local_epoch;
+            self.upsert_cluster_record(record.clone());
+            if self.local_epoch != epoch_before {
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.local_epoch += 1;
+        }
+        changed
+    }
+}
@@ -0,0 +1,26 @@
+use crate::broker::replication::{MetaReplicator, ReplicationError};
+use crate::broker::store::MetaStore;
+
+/// Pulls whatever a peer broker's replicator reports as different and merges
+/// it into `store`. Replacing the old "blind overwrite on any difference"
+/// behavior with `MetaStore::merge` is what lets two brokers that each took
+/// writes during a network partition converge instead of one side's writes
+/// getting silently discarded on reconnect. Only cluster records come
+/// through the replicator, not the peer's tombstones, so a cluster deleted
+/// on the peer is not removed from `store` here.
+pub async fn sync_with_peer<R: MetaReplicator>(
+    store: &mut MetaStore,
+    replicator: &R,
+    peer_address: String,
+) -> Result<bool, ReplicationError> {
+    let diff = replicator.fetch_diff(peer_address, store).await?;
+    if diff.is_empty() {
+        return Ok(false);
+    }
+
+    let mut peer_view = MetaStore::new("__replication_diff__".to_string());
+    for record in diff {
+        peer_view.upsert_cluster_record(record);
+    }
+    Ok(store.merge(&peer_view))
+}
@@ -23,7 +23,11 @@ extern crate chashmap;
 extern crate chrono;
 extern crate crossbeam_channel;
 extern crate itertools;
+#[macro_use]
+extern crate lazy_static;
 extern crate memchr;
+extern crate prometheus;
+extern crate sled;
 extern crate zstd;
 
 pub mod broker;
@@ -1,4 +1,9 @@
 use super::task::{ScanResponse, SlotRangeArray};
+use crate::broker::metrics::{
+    ACTIVE_DELETE_KEYS_TASKS, DELETE_KEYS_SCAN_CURSOR, DELETE_KEYS_SLOT_RANGES,
+    KEYS_DELETED_TOTAL, KEYS_SCANNED_TOTAL, MIGRATION_TASK_STATE, MIGRATION_STATE_DELETING_KEYS,
+    MIGRATION_STATE_FINISHED,
+};
 use crate::common::cluster::{DBName, SlotRange};
 use crate::common::config::AtomicMigrationConfig;
 use crate::common::db::HostDBMap;
@@ -56,10 +61,26 @@ impl DeleteKeysTaskMap {
         for (dbname, nodes) in self.task_map.iter() {
             let new_nodes = match local_db_map.get_map().get(dbname) {
                 Some(nodes) => nodes,
-                None => continue,
+                None => {
+                    for address in nodes.keys() {
+                        ACTIVE_DELETE_KEYS_TASKS
+                            .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                            .set(0);
+                        MIGRATION_TASK_STATE
+                            .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                            .set(MIGRATION_STATE_FINISHED);
+                    }
+                    continue;
+                }
             };
             for (address, task) in nodes.iter() {
                 if new_nodes.get(address).is_none() {
+                    ACTIVE_DELETE_KEYS_TASKS
+                        .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                        .set(0);
+                    MIGRATION_TASK_STATE
+                        .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                        .set(MIGRATION_STATE_FINISHED);
                     continue;
                 }
                 let db = new_task_map
@@ -72,10 +93,21 @@ impl DeleteKeysTaskMap {
         // Add new tasks
         for (dbname, nodes) in left_slots_after_change.into_iter() {
             for (address, slots) in nodes.into_iter() {
+                ACTIVE_DELETE_KEYS_TASKS
+                    .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                    .set(1);
+                MIGRATION_TASK_STATE
+                    .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                    .set(MIGRATION_STATE_DELETING_KEYS);
+                DELETE_KEYS_SLOT_RANGES
+                    .with_label_values(&[dbname.to_string().as_str(), address.as_str()])
+                    .set(slots.len() as i64);
+
                 let db = new_task_map
                     .entry(dbname.clone())
                     .or_insert_with(HashMap::new);
                 let task = Arc::new(DeleteKeysTask::new(
+                    dbname.clone(),
                     address.clone(),
                     slots,
                     client_factory.clone(),
@@ -100,6 +132,7 @@ type ScanDelFuture = Pin<Box<dyn Future<Output = Result<(), MigrationError>> + S
 type MigrationResult = Result<(), MigrationError>;
 
 pub struct DeleteKeysTask {
+    db: DBName,
     address: String,
     slot_ranges: SlotRangeArray,
     _handle: FutureAutoStopHandle, // once this task get dropped, the future will stop.
@@ -108,6 +141,7 @@ pub struct DeleteKeysTask {
 
 impl DeleteKeysTask {
     fn new<F: RedisClientFactory>(
+        db: DBName,
         address: String,
         slot_ranges: Vec<SlotRange>,
         client_factory: Arc<F>,
@@ -120,9 +154,15 @@ impl DeleteKeysTask {
         let slot_ranges = SlotRangeArray {
             ranges: slot_ranges,
         };
-        let (fut, handle) =
-            Self::gen_future(address.clone(), slot_ranges.clone(), client_factory, config);
+        let (fut, handle) = Self::gen_future(
+            db.clone(),
+            address.clone(),
+            slot_ranges.clone(),
+            client_factory,
+            config,
+        );
         Self {
+            db,
             address,
             slot_ranges,
             _handle: handle,
@@ -135,6 +175,7 @@ impl DeleteKeysTask {
     }
 
     fn gen_future<F: RedisClientFactory>(
+        db: DBName,
         address: String,
         slot_ranges: SlotRangeArray,
         client_factory: Arc<F>,
@@ -147,9 +188,9 @@ impl DeleteKeysTask {
         let send = keep_connecting_and_sending(
             data,
             client_factory,
-            address,
+            address.clone(),
             interval,
-            move |data, client| Self::scan_and_delete_keys(data, client, scan_count),
+            move |data, client| Self::scan_and_delete_keys(db.clone(), address.clone(), data, client, scan_count),
         );
         let (send, handle) = new_auto_drop_future(send);
         let send = send.map(|opt| match opt {
@@ -160,6 +201,8 @@ impl DeleteKeysTask {
     }
 
     async fn scan_and_delete_keys_impl<C: RedisClient>(
+        db: DBName,
+        address: String,
         data: (SlotRangeArray, u64),
         client: &mut C,
         scan_count: u64,
@@ -177,6 +220,15 @@ impl DeleteKeysTask {
         let ScanResponse { next_index, keys } =
             ScanResponse::parse_scan(resp).ok_or_else(|| RedisClientError::InvalidReply)?;
 
+        let labels = [db.to_string(), address.clone()];
+        let label_refs: Vec<&str> = labels.iter().map(String::as_str).collect();
+        KEYS_SCANNED_TOTAL
+            .with_label_values(&label_refs)
+            .inc_by(keys.len() as u64);
+        DELETE_KEYS_SCAN_CURSOR
+            .with_label_values(&label_refs)
+            .set(next_index as i64);
+
         let keys: Vec<Vec<u8>> = keys
             .into_iter()
             .filter(|k| !slot_ranges.is_key_inside(k.as_slice()))
@@ -188,6 +240,7 @@ impl DeleteKeysTask {
 
         let mut del_cmd = vec!["DEL".to_string().into_bytes()];
         del_cmd.extend_from_slice(keys.as_slice());
+        let deleted_count = keys.len() as u64;
         let resp = client.execute_single(del_cmd).await?;
 
         match resp {
@@ -195,16 +248,23 @@ impl DeleteKeysTask {
                 error!("failed to delete keys: {:?}", err);
                 Err(RedisClientError::InvalidReply)
             }
-            _ => Ok((slot_ranges, next_index)),
+            _ => {
+                KEYS_DELETED_TOTAL
+                    .with_label_values(&label_refs)
+                    .inc_by(deleted_count);
+                Ok((slot_ranges, next_index))
+            }
         }
     }
 
     fn scan_and_delete_keys<C: RedisClient>(
+        db: DBName,
+        address: String,
         data: (SlotRangeArray, u64),
         client: &mut C,
         scan_count: u64,
     ) -> Pin<Box<dyn Future<Output = ScanDelResult> + Send + '_>> {
-        Box::pin(Self::scan_and_delete_keys_impl(data, client, scan_count))
+        Box::pin(Self::scan_and_delete_keys_impl(db, address, data, client, scan_count))
     }
 
     pub fn get_address(&self) -> String {